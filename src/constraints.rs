@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+
+/// Limits and allow-lists enforced while a [`Multipart`](crate::Multipart) stream is read.
+///
+/// Pass a `Constraints` to [`Multipart::with_constraints`](crate::Multipart::with_constraints)
+/// to bound how much of an untrusted upload gets buffered before `multer` gives up, and to
+/// restrict which field names and content types are accepted at all.
+///
+/// # Examples
+///
+/// ```
+/// use multer::{Constraints, SizeLimit};
+///
+/// let constraints = Constraints::new()
+///     .allowed_fields(vec!["avatar", "caption"])
+///     .size_limit(SizeLimit::new().per_field(10 * 1024 * 1024).whole_stream(50 * 1024 * 1024));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Constraints {
+    pub(crate) size_limit: SizeLimit,
+    pub(crate) allowed_fields: Option<HashSet<String>>,
+    pub(crate) allowed_content_types: Option<HashSet<String>>,
+}
+
+impl Constraints {
+    /// Create an empty set of constraints with no limits or allow-lists configured.
+    pub fn new() -> Self {
+        Constraints::default()
+    }
+
+    /// Set the size limits enforced per field and/or across the whole stream.
+    pub fn size_limit(mut self, size_limit: SizeLimit) -> Self {
+        self.size_limit = size_limit;
+        self
+    }
+
+    /// Only accept fields whose `name` is one of `names`; any other field is rejected.
+    pub fn allowed_fields<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_fields = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Only accept fields whose `Content-Type` is one of `content_types`; any other field
+    /// is rejected.
+    pub fn allowed_content_types<I, S>(mut self, content_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_content_types = Some(content_types.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Check a field's `name` and `Content-Type` against the configured allow-lists.
+    pub(crate) fn validate_field(&self, name: Option<&str>, content_type: Option<&mime::Mime>) -> crate::Result<()> {
+        if let Some(allowed) = &self.allowed_fields {
+            if !name.is_some_and(|name| allowed.contains(name)) {
+                return Err(crate::Error::UnknownField {
+                    field_name: name.map(str::to_owned),
+                });
+            }
+        }
+
+        if let Some(allowed) = &self.allowed_content_types {
+            let content_type = content_type.map(|mime| mime.essence_str());
+
+            if !content_type.is_some_and(|content_type| allowed.contains(content_type)) {
+                return Err(crate::Error::DisallowedContentType {
+                    content_type: content_type.map(str::to_owned),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Byte limits enforced per field and/or across the whole multipart stream, along with a
+/// cap on the headers of any individual part.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SizeLimit {
+    pub(crate) per_field: Option<u64>,
+    pub(crate) whole_stream: Option<u64>,
+    pub(crate) per_field_headers: Option<usize>,
+}
+
+impl SizeLimit {
+    /// Create a `SizeLimit` with no limits configured.
+    pub fn new() -> Self {
+        SizeLimit::default()
+    }
+
+    /// Cap the number of bytes read from any single field.
+    pub fn per_field(mut self, limit: u64) -> Self {
+        self.per_field = Some(limit);
+        self
+    }
+
+    /// Cap the number of bytes read across every field in the stream.
+    pub fn whole_stream(mut self, limit: u64) -> Self {
+        self.whole_stream = Some(limit);
+        self
+    }
+
+    /// Cap the number of header bytes accepted for a single part.
+    pub fn per_field_headers(mut self, limit: usize) -> Self {
+        self.per_field_headers = Some(limit);
+        self
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt;
+
+/// The error type used throughout this crate.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A field exceeded the per-field size limit configured via
+    /// [`SizeLimit::per_field`](crate::SizeLimit::per_field).
+    FieldSizeExceeded {
+        /// The configured limit, in bytes.
+        limit: u64,
+        /// The name of the field that exceeded the limit, if any.
+        field_name: Option<String>,
+    },
+    /// The whole multipart stream exceeded the limit configured via
+    /// [`SizeLimit::whole_stream`](crate::SizeLimit::whole_stream).
+    StreamSizeExceeded {
+        /// The configured limit, in bytes.
+        limit: u64,
+    },
+    /// A part's headers exceeded the limit configured via
+    /// [`SizeLimit::per_field_headers`](crate::SizeLimit::per_field_headers).
+    HeadersSizeExceeded {
+        /// The configured limit, in bytes.
+        limit: usize,
+    },
+    /// A field's `name` wasn't in the allow-list configured via
+    /// [`Constraints::allowed_fields`](crate::Constraints::allowed_fields).
+    UnknownField {
+        /// The name of the rejected field, if any.
+        field_name: Option<String>,
+    },
+    /// A field's `Content-Type` wasn't in the allow-list configured via
+    /// [`Constraints::allowed_content_types`](crate::Constraints::allowed_content_types).
+    DisallowedContentType {
+        /// The rejected content type, if any.
+        content_type: Option<String>,
+    },
+    /// Reading from the underlying stream failed.
+    StreamReadFailed(Box<dyn std::error::Error + Send + Sync>),
+    /// I/O while spooling a field's data to disk failed.
+    Io(std::io::Error),
+    /// A generic error for cases that don't map onto a more specific variant.
+    Generic(String),
+    /// An error with an attached explanation of what was being attempted.
+    WithContext(Box<Error>, String),
+}
+
+impl Error {
+    pub(crate) fn new(msg: impl Into<String>) -> Self {
+        Error::Generic(msg.into())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::FieldSizeExceeded { limit, field_name } => write!(
+                f,
+                "field `{}` exceeded the {} byte per-field size limit",
+                field_name.as_deref().unwrap_or(""),
+                limit
+            ),
+            Error::StreamSizeExceeded { limit } => {
+                write!(f, "the multipart stream exceeded the {} byte total size limit", limit)
+            }
+            Error::HeadersSizeExceeded { limit } => {
+                write!(f, "a part's headers exceeded the {} byte size limit", limit)
+            }
+            Error::UnknownField { field_name } => write!(
+                f,
+                "field `{}` is not an allowed field name",
+                field_name.as_deref().unwrap_or("")
+            ),
+            Error::DisallowedContentType { content_type } => write!(
+                f,
+                "field content type `{}` is not allowed",
+                content_type.as_deref().unwrap_or("")
+            ),
+            Error::StreamReadFailed(err) => write!(f, "couldn't read data from the stream: {}", err),
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Generic(msg) => write!(f, "{}", msg),
+            Error::WithContext(err, ctx) => write!(f, "{}: {}", ctx, err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::StreamReadFailed(err) => Some(err.as_ref()),
+            Error::Io(err) => Some(err),
+            Error::WithContext(err, _) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// Result type used throughout this crate.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Attach context to an [`Error`].
+pub(crate) trait ErrorExt {
+    fn context(self, ctx: impl Into<String>) -> Self;
+}
+
+impl ErrorExt for Error {
+    fn context(self, ctx: impl Into<String>) -> Self {
+        Error::WithContext(Box::new(self), ctx.into())
+    }
+}
+
+/// Attach context to a `Result` whose error converts into [`Error`].
+pub(crate) trait ResultExt<T> {
+    fn context(self, ctx: impl Into<String>) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> ResultExt<T> for std::result::Result<T, E> {
+    fn context(self, ctx: impl Into<String>) -> Result<T> {
+        self.map_err(|err| err.into().context(ctx))
+    }
+}
@@ -1,7 +1,5 @@
 use crate::state::{MultipartState, StreamingStage};
-#[cfg(feature = "json")]
-use crate::ResultExt;
-use crate::{constants, ErrorExt};
+use crate::{ErrorExt, ResultExt};
 use bytes::{Bytes, BytesMut};
 use encoding_rs::{Encoding, UTF_8};
 use futures::stream::{Stream, TryStreamExt};
@@ -11,10 +9,14 @@ use serde::de::DeserializeOwned;
 #[cfg(feature = "json")]
 use serde_json;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::ops::DerefMut;
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
+use tempfile::TempPath;
+use tokio::io::AsyncWriteExt;
 
 /// A single field in a multipart stream.
 ///
@@ -55,6 +57,8 @@ pub struct Field {
     state: Arc<Mutex<MultipartState>>,
     headers: HeaderMap,
     done: bool,
+    nested: bool,
+    bytes_read: u64,
     meta: FieldMeta,
 }
 
@@ -62,46 +66,229 @@ struct FieldMeta {
     name: Option<String>,
     file_name: Option<String>,
     content_type: Option<mime::Mime>,
+    content_disposition_params: HashMap<String, String>,
     idx: usize,
 }
 
 impl Field {
     pub(crate) fn new(state: Arc<Mutex<MultipartState>>, headers: HeaderMap, idx: usize) -> Self {
-        let (name, file_name) = Self::parse_content_disposition(&headers);
+        let (name, file_name, content_disposition_params) = Self::parse_content_disposition(&headers);
         let content_type = Self::parse_content_type(&headers);
 
         Field {
             state,
             headers,
             done: false,
+            nested: false,
+            bytes_read: 0,
             meta: FieldMeta {
                 name,
                 file_name,
                 content_type,
+                content_disposition_params,
                 idx,
             },
         }
     }
 
-    fn parse_content_disposition(headers: &HeaderMap) -> (Option<String>, Option<String>) {
-        let content_disposition = headers
+    /// The boundary of a nested `multipart/*` payload carried by this field, if any.
+    fn nested_boundary(&self) -> Option<String> {
+        self.content_type()
+            .filter(|mime| mime.type_() == mime::MULTIPART)
+            .and_then(|mime| mime.get_param("boundary"))
+            .map(|boundary| boundary.as_str().to_owned())
+    }
+
+    /// Descend into a nested `multipart/*` payload carried by this field.
+    ///
+    /// Some clients (for example a browser submitting several files under one form
+    /// control) wrap those files in a `multipart/mixed` body and send that as the
+    /// content of an outer field. When [`content_type()`](Self::content_type) is a
+    /// `multipart/*` type with a `boundary` parameter, this returns a
+    /// [`Multipart`](crate::Multipart) scoped to that inner boundary and driven off
+    /// the same underlying stream, with its own `next_field()` sequence.
+    ///
+    /// The parent [`Multipart`](crate::Multipart) will not yield its next field until
+    /// the returned one has been fully drained or dropped; see the warning on
+    /// [`Drop for Field`](#impl-Drop). The outer boundary is restored once that
+    /// happens, so the parent resumes splitting on the right delimiter.
+    ///
+    /// Returns `None` if this field isn't itself a `multipart/*` payload.
+    pub fn into_multipart(mut self) -> Option<crate::Multipart> {
+        let boundary = self.nested_boundary()?;
+
+        {
+            let mut mutex_guard = self.state.lock().ok()?;
+            let state: &mut MultipartState = mutex_guard.deref_mut();
+            let outer_boundary = std::mem::replace(&mut state.boundary, boundary);
+            state.nested_boundaries.push(outer_boundary);
+            state.stage = StreamingStage::ReadingNestedBoundary;
+        }
+
+        self.nested = true;
+
+        Some(crate::Multipart::with_state(self.state.clone()))
+    }
+
+    /// Parse the `Content-Disposition` header into its `name`/`filename` and any
+    /// remaining parameters, per [RFC 6266](https://tools.ietf.org/html/rfc6266) and the
+    /// extended `ext-value` syntax of [RFC 5987](https://tools.ietf.org/html/rfc5987).
+    ///
+    /// When both `filename` and `filename*` are present, the RFC 5987-encoded
+    /// `filename*` wins, same for `name`/`name*`.
+    pub(crate) fn parse_content_disposition(
+        headers: &HeaderMap,
+    ) -> (Option<String>, Option<String>, HashMap<String, String>) {
+        let content_disposition = match headers
             .get(header::CONTENT_DISPOSITION)
-            .and_then(|val| val.to_str().ok());
+            .and_then(|val| val.to_str().ok())
+        {
+            Some(val) => val,
+            None => return (None, None, HashMap::new()),
+        };
+
+        let mut name = None;
+        let mut file_name = None;
+        let mut name_is_extended = false;
+        let mut file_name_is_extended = false;
+        let mut params = HashMap::new();
+
+        // The first segment is the disposition type (e.g. `form-data`), not a parameter.
+        for part in Self::split_params(content_disposition).into_iter().skip(1) {
+            let part = part.trim();
+
+            let eq_idx = match part.find('=') {
+                Some(idx) => idx,
+                None => continue,
+            };
+
+            let (raw_key, raw_value) = part.split_at(eq_idx);
+            let raw_value = raw_value[1..].trim();
+
+            let (key, is_extended) = match raw_key.trim().strip_suffix('*') {
+                Some(key) => (key, true),
+                None => (raw_key.trim(), false),
+            };
+
+            let value = if is_extended {
+                match Self::decode_ext_value(raw_value) {
+                    Some(value) => value,
+                    None => continue,
+                }
+            } else {
+                Self::unquote(raw_value)
+            };
+
+            match (key.to_ascii_lowercase().as_str(), is_extended) {
+                ("name", extended) if extended || !name_is_extended => {
+                    name = Some(value);
+                    name_is_extended = extended;
+                }
+                ("filename", extended) if extended || !file_name_is_extended => {
+                    file_name = Some(value);
+                    file_name_is_extended = extended;
+                }
+                ("name", _) | ("filename", _) => {}
+                (key, _) => {
+                    params.insert(key.to_owned(), value);
+                }
+            }
+        }
+
+        (name, file_name, params)
+    }
+
+    /// Split a `Content-Disposition` value on `;`, except for `;`s that appear inside a
+    /// `quoted-string` (a `\"` inside quotes doesn't end the quoted-string either).
+    fn split_params(value: &str) -> Vec<&str> {
+        let mut parts = Vec::new();
+        let mut start = 0;
+        let mut in_quotes = false;
+        let mut chars = value.char_indices();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                '\\' if in_quotes => {
+                    // Skip the escaped character so a `\"` doesn't toggle `in_quotes`.
+                    chars.next();
+                }
+                ';' if !in_quotes => {
+                    parts.push(&value[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+
+        parts.push(&value[start..]);
+        parts
+    }
+
+    /// Strip the surrounding quotes from a `quoted-string` parameter value, unescaping
+    /// any `\"`/`\\` pairs. Returns `value` unchanged if it isn't quoted.
+    fn unquote(value: &str) -> String {
+        if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+            let inner = &value[1..value.len() - 1];
+            let mut unescaped = String::with_capacity(inner.len());
+            let mut chars = inner.chars();
+
+            while let Some(c) = chars.next() {
+                if c == '\\' {
+                    if let Some(escaped) = chars.next() {
+                        unescaped.push(escaped);
+                        continue;
+                    }
+                }
+
+                unescaped.push(c);
+            }
+
+            unescaped
+        } else {
+            value.to_owned()
+        }
+    }
 
-        let name = content_disposition
-            .and_then(|val| constants::CONTENT_DISPOSITION_FIELD_NAME_RE.captures(val))
-            .and_then(|cap| cap.get(1))
-            .map(|m| m.as_str().to_owned());
+    /// Decode an RFC 5987 `ext-value` (`charset'lang'pct-encoded`) into a `String`.
+    fn decode_ext_value(value: &str) -> Option<String> {
+        let mut parts = value.splitn(3, '\'');
+        let charset = parts.next()?;
+        let _lang = parts.next()?;
+        let encoded = parts.next()?;
 
-        let file_name = content_disposition
-            .and_then(|val| constants::CONTENT_DISPOSITION_FILE_NAME_RE.captures(val))
-            .and_then(|cap| cap.get(1))
-            .map(|m| m.as_str().to_owned());
+        let encoding = Encoding::for_label(charset.as_bytes()).unwrap_or(UTF_8);
+        let decoded_bytes = Self::percent_decode(encoded);
+        let (text, _, _) = encoding.decode(&decoded_bytes);
 
-        (name, file_name)
+        Some(text.into_owned())
     }
 
-    fn parse_content_type(headers: &HeaderMap) -> Option<mime::Mime> {
+    /// Percent-decode a `pct-encoded` string into raw bytes.
+    fn percent_decode(input: &str) -> Vec<u8> {
+        let bytes = input.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+
+        while i < bytes.len() {
+            if bytes[i] == b'%' && i + 2 < bytes.len() {
+                if let Ok(hex) = std::str::from_utf8(&bytes[i + 1..=i + 2]) {
+                    if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                }
+            }
+
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+
+        decoded
+    }
+
+    pub(crate) fn parse_content_type(headers: &HeaderMap) -> Option<mime::Mime> {
         headers
             .get(header::CONTENT_TYPE)
             .and_then(|val| val.to_str().ok())
@@ -110,12 +297,12 @@ impl Field {
 
     /// The field name found in the [`Content-Disposition`](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Content-Disposition) header.
     pub fn name(&self) -> Option<&str> {
-        self.meta.name.as_ref().map(|name| name.as_str())
+        self.meta.name.as_deref()
     }
 
     /// The file name found in the [`Content-Disposition`](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Content-Disposition) header.
     pub fn file_name(&self) -> Option<&str> {
-        self.meta.file_name.as_ref().map(|file_name| file_name.as_str())
+        self.meta.file_name.as_deref()
     }
 
     /// Get the content type of the field.
@@ -123,6 +310,12 @@ impl Field {
         self.meta.content_type.as_ref()
     }
 
+    /// Get any `Content-Disposition` parameters other than `name` and `filename`/`filename*`,
+    /// keyed by their lowercased parameter name.
+    pub fn content_disposition_params(&self) -> &HashMap<String, String> {
+        &self.meta.content_disposition_params
+    }
+
     /// Get a map of headers as [`HeaderMap`](https://docs.rs/http/0.2.1/http/header/struct.HeaderMap.html).
     pub fn headers(&self) -> &HeaderMap {
         &self.headers
@@ -161,6 +354,38 @@ impl Field {
         Ok(buf.freeze())
     }
 
+    /// Save the field's data, keeping it in memory while it stays below a configurable
+    /// threshold and spilling the rest to a temporary file once it's crossed.
+    ///
+    /// This is the preferred way to accept large file uploads, since unlike
+    /// [`bytes()`](Self::bytes) it never has to hold the whole field in memory at once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multer::{Multipart, SavedField};
+    /// use bytes::Bytes;
+    /// use std::convert::Infallible;
+    /// use futures::stream::once;
+    ///
+    /// # async fn run() {
+    /// let data = "--X-BOUNDARY\r\nContent-Disposition: form-data; name=\"My Field\"\r\n\r\nabcd\r\n--X-BOUNDARY--\r\n";
+    /// let stream = once(async move { Result::<Bytes, Infallible>::Ok(Bytes::from(data)) });
+    /// let mut multipart = Multipart::new(stream, "X-BOUNDARY");
+    ///
+    /// while let Some(field) = multipart.next_field().await.unwrap() {
+    ///     match field.save().memory_threshold(1024).await_save().await.unwrap() {
+    ///         SavedField::Bytes(bytes) => assert_eq!(bytes.len(), 4),
+    ///         SavedField::SavedFile { .. } => unreachable!("field is smaller than the threshold"),
+    ///     }
+    /// }
+    /// # }
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(run());
+    /// ```
+    pub fn save(self) -> SaveBuilder {
+        SaveBuilder::new(self)
+    }
+
     /// Stream a chunk of the field data.
     ///
     /// When the field data has been exhausted, this will return None.
@@ -338,6 +563,114 @@ impl Field {
     }
 }
 
+/// The outcome of a [`Field::save`] operation: either the field stayed below the
+/// memory threshold, or it was spilled to a temporary file on disk.
+#[derive(Debug)]
+pub enum SavedField {
+    /// The field's data stayed below the memory threshold and is held in memory.
+    Bytes(Bytes),
+    /// The field's data crossed the memory threshold and was streamed to a file.
+    SavedFile {
+        /// Path of the file the field's data was written to.
+        path: PathBuf,
+        /// Total number of bytes written to `path`.
+        size: u64,
+    },
+}
+
+/// Builds a [`Field::save`] operation.
+///
+/// Buffers the field's data in memory up to [`memory_threshold`](Self::memory_threshold)
+/// bytes (512 KiB by default); once that's crossed, the buffered bytes and everything
+/// read afterwards are streamed to a temporary file in [`with_dir`](Self::with_dir)
+/// (the system temporary directory by default) instead.
+pub struct SaveBuilder {
+    field: Field,
+    memory_threshold: usize,
+    dir: Option<PathBuf>,
+}
+
+impl SaveBuilder {
+    const DEFAULT_MEMORY_THRESHOLD: usize = 512 * 1024;
+
+    fn new(field: Field) -> Self {
+        SaveBuilder {
+            field,
+            memory_threshold: Self::DEFAULT_MEMORY_THRESHOLD,
+            dir: None,
+        }
+    }
+
+    /// Set the number of bytes to buffer in memory before spilling to disk.
+    pub fn memory_threshold(mut self, threshold: usize) -> Self {
+        self.memory_threshold = threshold;
+        self
+    }
+
+    /// Write the spooled file into `dir` instead of the system temporary directory.
+    pub fn with_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = Some(dir.into());
+        self
+    }
+
+    /// Drive the field to completion, returning the in-memory bytes or the path of the
+    /// file they were spooled to.
+    ///
+    /// If a later chunk fails to read, any spool file created so far is removed rather
+    /// than left behind half-written.
+    pub async fn await_save(mut self) -> crate::Result<SavedField> {
+        let mut buf = BytesMut::new();
+        // `TempPath` deletes the file on drop unless `keep()`d, so an error anywhere
+        // below (via `?`) cleans up the partially written spool file automatically.
+        let mut spool: Option<(tokio::fs::File, TempPath, u64)> = None;
+
+        while let Some(chunk) = self.field.chunk().await? {
+            match spool.as_mut() {
+                Some((handle, _, written)) => {
+                    handle.write_all(&chunk).await.context("Couldn't write field data to the spool file")?;
+
+                    *written += chunk.len() as u64;
+                }
+                None => {
+                    buf.extend_from_slice(&chunk);
+
+                    if buf.len() > self.memory_threshold {
+                        let dir = self.dir.clone().unwrap_or_else(std::env::temp_dir);
+
+                        let named_file = tempfile::Builder::new()
+                            .prefix("multer-field-")
+                            .suffix(".part")
+                            .tempfile_in(&dir)
+                            .context("Couldn't create the spool file")?;
+
+                        let (std_file, temp_path) = named_file.into_parts();
+                        let mut handle = tokio::fs::File::from_std(std_file);
+
+                        handle.write_all(&buf).await.context("Couldn't write field data to the spool file")?;
+
+                        let written = buf.len() as u64;
+                        buf.clear();
+                        spool = Some((handle, temp_path, written));
+                    }
+                }
+            }
+        }
+
+        match spool {
+            Some((mut handle, temp_path, size)) => {
+                handle.flush().await.context("Couldn't flush the spool file")?;
+
+                let path = temp_path
+                    .keep()
+                    .map_err(|err| crate::Error::new(err.to_string()).context("Couldn't persist the spool file"))?;
+
+                Ok(SavedField::SavedFile { path, size })
+            }
+            None => Ok(SavedField::Bytes(buf.freeze())),
+        }
+    }
+}
+
 impl Stream for Field {
     type Item = Result<Bytes, crate::Error>;
 
@@ -364,14 +697,38 @@ impl Stream for Field {
         }
 
         match stream_buffer.read_field_data(state.boundary.as_str()) {
-            Ok(Some((true, bytes))) => {
+            Ok(Some((eof, bytes))) => {
+                let bytes_read = self.bytes_read + bytes.len() as u64;
+
+                if let Some(constraints) = state.constraints.as_ref() {
+                    if let Some(limit) = constraints.size_limit.per_field {
+                        if bytes_read > limit {
+                            return Poll::Ready(Some(Err(crate::Error::FieldSizeExceeded {
+                                limit,
+                                field_name: self.meta.name.clone(),
+                            })));
+                        }
+                    }
+
+                    if let Some(limit) = constraints.size_limit.whole_stream {
+                        state.total_bytes_read += bytes.len() as u64;
+
+                        if state.total_bytes_read > limit {
+                            return Poll::Ready(Some(Err(crate::Error::StreamSizeExceeded { limit })));
+                        }
+                    }
+                }
+
                 drop(mutex_guard);
 
-                self.done = true;
+                self.bytes_read = bytes_read;
+
+                if eof {
+                    self.done = true;
+                }
 
                 Poll::Ready(Some(Ok(bytes)))
             }
-            Ok(Some((false, bytes))) => Poll::Ready(Some(Ok(bytes))),
             Ok(None) => Poll::Pending,
             Err(err) => Poll::Ready(Some(Err(err))),
         }
@@ -393,6 +750,14 @@ impl Drop for Field {
 
         let state: &mut MultipartState = mutex_guard.deref_mut();
 
+        if self.nested {
+            // The nested `Multipart` returned by `into_multipart` owns `state` from
+            // here on and will hand the parent back once it's fully drained; racing
+            // it by resetting the stage ourselves would let the parent read past the
+            // inner boundary before the nested stream is done with it.
+            return;
+        }
+
         if self.done {
             state.stage = StreamingStage::ReadingBoundary;
         } else {
@@ -402,7 +767,74 @@ impl Drop for Field {
         state.is_prev_field_consumed = true;
 
         if let Some(waker) = state.next_field_waker.take() {
-            waker.clone().wake();
+            waker.wake();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unquote_handles_escaped_quotes() {
+        assert_eq!(Field::unquote(r#""a\"b""#), "a\"b");
+        assert_eq!(Field::unquote("unquoted"), "unquoted");
+    }
+
+    #[test]
+    fn split_params_ignores_semicolons_inside_quotes() {
+        let params = Field::split_params(r#"form-data; name="my;field"; filename="a;b.txt""#);
+
+        assert_eq!(params, vec!["form-data", r#" name="my;field""#, r#" filename="a;b.txt""#]);
+    }
+
+    #[test]
+    fn decode_ext_value_decodes_rfc_5987() {
+        let decoded = Field::decode_ext_value("UTF-8''%e2%82%ac.txt").unwrap();
+
+        assert_eq!(decoded, "\u{20ac}.txt");
+    }
+
+    #[test]
+    fn parse_content_disposition_prefers_filename_star() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_DISPOSITION,
+            "form-data; name=\"file\"; filename=\"fallback.txt\"; filename*=UTF-8''%e2%82%ac.txt"
+                .parse()
+                .unwrap(),
+        );
+
+        let (name, file_name, _) = Field::parse_content_disposition(&headers);
+
+        assert_eq!(name.as_deref(), Some("file"));
+        assert_eq!(file_name.as_deref(), Some("\u{20ac}.txt"));
+    }
+
+    #[test]
+    fn parse_content_disposition_handles_semicolon_in_quoted_filename() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_DISPOSITION,
+            "form-data; name=\"file\"; filename=\"a;b.txt\"".parse().unwrap(),
+        );
+
+        let (_, file_name, _) = Field::parse_content_disposition(&headers);
+
+        assert_eq!(file_name.as_deref(), Some("a;b.txt"));
+    }
+
+    #[test]
+    fn parse_content_disposition_collects_extra_params() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_DISPOSITION,
+            "form-data; name=\"file\"; custom=\"value\"".parse().unwrap(),
+        );
+
+        let (_, _, params) = Field::parse_content_disposition(&headers);
+
+        assert_eq!(params.get("custom").map(String::as_str), Some("value"));
+    }
+}
@@ -0,0 +1,17 @@
+//! An async parser for `multipart/form-data` content type in Rust.
+//!
+//! [`Multipart`] reads a `multipart/form-data` body off any `Stream` of bytes and yields
+//! its [`Field`]s one at a time.
+
+mod constraints;
+mod error;
+mod field;
+mod multipart;
+mod state;
+
+pub use crate::constraints::{Constraints, SizeLimit};
+pub use crate::error::{Error, Result};
+pub use crate::field::{Field, SaveBuilder, SavedField};
+pub use crate::multipart::Multipart;
+
+pub(crate) use crate::error::{ErrorExt, ResultExt};
@@ -0,0 +1,270 @@
+use crate::constraints::Constraints;
+use crate::field::Field;
+use crate::state::{BoundaryLine, MultipartState, StreamBuffer, StreamingStage};
+use crate::ErrorExt;
+use bytes::Bytes;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+/// Parses a `multipart/form-data` stream into its [`Field`]s.
+///
+/// # Examples
+///
+/// ```
+/// use multer::Multipart;
+/// use bytes::Bytes;
+/// use std::convert::Infallible;
+/// use futures::stream::once;
+///
+/// # async fn run() {
+/// let data = "--X-BOUNDARY\r\nContent-Disposition: form-data; name=\"My Field\"\r\n\r\nabcd\r\n--X-BOUNDARY--\r\n";
+/// let stream = once(async move { Result::<Bytes, Infallible>::Ok(Bytes::from(data)) });
+/// let mut multipart = Multipart::new(stream, "X-BOUNDARY");
+///
+/// while let Some(field) = multipart.next_field().await.unwrap() {
+///     let content = field.text().await.unwrap();
+///     assert_eq!(content, "abcd");
+/// }
+/// # }
+/// # tokio::runtime::Runtime::new().unwrap().block_on(run());
+/// ```
+pub struct Multipart {
+    state: Arc<Mutex<MultipartState>>,
+    /// Whether this `Multipart` was built off [`Field::into_multipart`](crate::Field::into_multipart)
+    /// and so owns an entry on `state.nested_boundaries` that must be restored, either
+    /// once it's drained (see `exhausted` below) or on `Drop`.
+    is_nested: bool,
+    /// Set once this `Multipart` has yielded `Ok(None)`, at which point the boundary it
+    /// owns (nested or not) has already been restored/finalized and `Drop` has nothing
+    /// left to do.
+    exhausted: bool,
+}
+
+impl Multipart {
+    /// Create a new `Multipart` with no size limits or allow-lists configured.
+    pub fn new<S, O, E>(stream: S, boundary: impl Into<String>) -> Self
+    where
+        S: futures::stream::Stream<Item = std::result::Result<O, E>> + Send + Sync + 'static,
+        O: Into<Bytes> + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>> + 'static,
+    {
+        Self::with_constraints(stream, boundary, Constraints::new())
+    }
+
+    /// Create a new `Multipart`, enforcing the given [`Constraints`] while it's read.
+    pub fn with_constraints<S, O, E>(stream: S, boundary: impl Into<String>, constraints: Constraints) -> Self
+    where
+        S: futures::stream::Stream<Item = std::result::Result<O, E>> + Send + Sync + 'static,
+        O: Into<Bytes> + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>> + 'static,
+    {
+        let state = MultipartState {
+            buffer: StreamBuffer::new(stream),
+            boundary: boundary.into(),
+            stage: StreamingStage::ReadingBoundary,
+            is_prev_field_consumed: true,
+            next_field_idx: 0,
+            next_field_waker: None,
+            nested_boundaries: Vec::new(),
+            constraints: Some(constraints),
+            total_bytes_read: 0,
+        };
+
+        Multipart {
+            state: Arc::new(Mutex::new(state)),
+            is_nested: false,
+            exhausted: false,
+        }
+    }
+
+    /// Build a `Multipart` that descends into a nested `multipart/*` payload, reusing the
+    /// inner boundary [`Field::into_multipart`](crate::Field::into_multipart) already
+    /// installed in the shared state, along with the constraints already held there.
+    pub(crate) fn with_state(state: Arc<Mutex<MultipartState>>) -> Self {
+        Multipart {
+            state,
+            is_nested: true,
+            exhausted: false,
+        }
+    }
+
+    /// Yield the next [`Field`] in the stream, or `None` once it's exhausted.
+    ///
+    /// Only one [`Field`] may be live at a time; see the warning on
+    /// [`Drop for Field`](crate::Field#impl-Drop).
+    pub async fn next_field(&mut self) -> crate::Result<Option<Field>> {
+        futures::future::poll_fn(|cx| self.poll_next_field(cx)).await
+    }
+
+    fn poll_next_field(&mut self, cx: &mut Context) -> Poll<crate::Result<Option<Field>>> {
+        let mut mutex_guard = match self.state.lock() {
+            Ok(lock) => lock,
+            Err(err) => {
+                return Poll::Ready(Err(
+                    crate::Error::new(err.to_string()).context("Couldn't lock the multipart state")
+                ));
+            }
+        };
+
+        let state: &mut MultipartState = &mut mutex_guard;
+
+        loop {
+            if let Err(err) = state.buffer.poll_stream(cx) {
+                return Poll::Ready(Err(err.context("Couldn't read data from the stream")));
+            }
+
+            match state.stage {
+                StreamingStage::CleaningPrevFieldData => match state.buffer.read_field_data(&state.boundary) {
+                    Ok(Some((eof, bytes))) => {
+                        if let Some(limit) = state.constraints.as_ref().and_then(|c| c.size_limit.whole_stream) {
+                            state.total_bytes_read += bytes.len() as u64;
+
+                            if state.total_bytes_read > limit {
+                                return Poll::Ready(Err(crate::Error::StreamSizeExceeded { limit }));
+                            }
+                        }
+
+                        if eof {
+                            state.stage = StreamingStage::ReadingBoundary;
+                        }
+                    }
+                    Ok(None) => {
+                        state.next_field_waker = Some(cx.waker().clone());
+                        return Poll::Pending;
+                    }
+                    Err(err) => return Poll::Ready(Err(err)),
+                },
+                StreamingStage::CleaningNestedBoundary => match state.buffer.skip_to_boundary_end(&state.boundary) {
+                    Ok(Some(())) => {
+                        if let Some(outer_boundary) = state.nested_boundaries.pop() {
+                            state.boundary = outer_boundary;
+                            // The nested body's own terminal line is consumed, but the
+                            // outer framing still owes us its "\r\n--boundary" delimiter
+                            // prefix (or epilogue bytes before it); drain that the same
+                            // way an abandoned field's trailing data is drained.
+                            state.stage = StreamingStage::CleaningPrevFieldData;
+                        } else {
+                            state.stage = StreamingStage::ReadingBoundary;
+                        }
+
+                        state.is_prev_field_consumed = true;
+                    }
+                    Ok(None) => {
+                        state.next_field_waker = Some(cx.waker().clone());
+                        return Poll::Pending;
+                    }
+                    Err(err) => return Poll::Ready(Err(err)),
+                },
+                StreamingStage::ReadingBoundary | StreamingStage::ReadingNestedBoundary => {
+                    match state.buffer.read_boundary(&state.boundary) {
+                        Ok(Some(BoundaryLine::Eof)) => {
+                            if let Some(outer_boundary) = state.nested_boundaries.pop() {
+                                state.boundary = outer_boundary;
+                                // As in `CleaningNestedBoundary`: the outer framing still
+                                // owes its own "\r\n--boundary" delimiter prefix (or
+                                // epilogue bytes before it), so drain that before treating
+                                // what's left as the outer boundary line.
+                                state.stage = StreamingStage::CleaningPrevFieldData;
+                                state.is_prev_field_consumed = true;
+
+                                if let Some(waker) = state.next_field_waker.take() {
+                                    waker.wake();
+                                }
+                            } else {
+                                state.stage = StreamingStage::Eof;
+                            }
+
+                            drop(mutex_guard);
+                            self.exhausted = true;
+
+                            return Poll::Ready(Ok(None));
+                        }
+                        Ok(Some(BoundaryLine::Next)) => state.stage = StreamingStage::ReadingHeaders,
+                        Ok(None) => {
+                            state.next_field_waker = Some(cx.waker().clone());
+                            return Poll::Pending;
+                        }
+                        Err(err) => return Poll::Ready(Err(err)),
+                    }
+                }
+                StreamingStage::ReadingHeaders => {
+                    let header_limit = state.constraints.as_ref().and_then(|c| c.size_limit.per_field_headers);
+
+                    match state.buffer.read_headers(header_limit) {
+                        Ok(Some(headers)) => {
+                            let (name, _file_name, _params) = Field::parse_content_disposition(&headers);
+                            let content_type = Field::parse_content_type(&headers);
+
+                            if let Some(constraints) = state.constraints.as_ref() {
+                                if let Err(err) = constraints.validate_field(name.as_deref(), content_type.as_ref()) {
+                                    state.stage = StreamingStage::CleaningPrevFieldData;
+                                    return Poll::Ready(Err(err));
+                                }
+                            }
+
+                            let idx = state.next_field_idx;
+                            state.next_field_idx += 1;
+                            state.stage = StreamingStage::ReadingField;
+                            state.is_prev_field_consumed = false;
+
+                            return Poll::Ready(Ok(Some(Field::new(self.state.clone(), headers, idx))));
+                        }
+                        Ok(None) => {
+                            state.next_field_waker = Some(cx.waker().clone());
+                            return Poll::Pending;
+                        }
+                        Err(err) => return Poll::Ready(Err(err)),
+                    }
+                }
+                StreamingStage::ReadingField => {
+                    // A `Field` is already live; `next_field` won't be polled again until
+                    // its `Drop` moves the stage on, per the "only one at a time" contract.
+                    state.next_field_waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+                StreamingStage::Eof => {
+                    drop(mutex_guard);
+                    self.exhausted = true;
+                    return Poll::Ready(Ok(None));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Multipart {
+    /// If this `Multipart` descended into a nested `multipart/*` payload (via
+    /// [`Field::into_multipart`](crate::Field::into_multipart)) and was dropped before it
+    /// was drained to `Eof`, arrange for the rest of that payload to be skipped and the
+    /// boundary it shadowed restored, so the parent `Multipart` resumes splitting on the
+    /// right delimiter instead of being wedged on the inner one.
+    ///
+    /// If a [`Field`] yielded by this `Multipart` is still live, its own `Drop` owns
+    /// moving the stage on; stepping on it here would race with that hand-back.
+    fn drop(&mut self) {
+        if !self.is_nested || self.exhausted {
+            return;
+        }
+
+        let mut mutex_guard = match self.state.lock() {
+            Ok(lock) => lock,
+            Err(err) => {
+                log::error!(
+                    "{}",
+                    crate::Error::new(err.to_string()).context("Couldn't lock the multipart state")
+                );
+                return;
+            }
+        };
+
+        if matches!(mutex_guard.stage, StreamingStage::ReadingField) {
+            return;
+        }
+
+        mutex_guard.stage = StreamingStage::CleaningNestedBoundary;
+
+        if let Some(waker) = mutex_guard.next_field_waker.take() {
+            waker.wake();
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use crate::constraints::Constraints;
+use crate::ErrorExt;
+use bytes::{Buf, Bytes, BytesMut};
+use futures::stream::{Stream, TryStreamExt};
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+/// What a `Multipart` is about to do the next time it's polled.
+pub(crate) enum StreamingStage {
+    ReadingBoundary,
+    /// Like `ReadingBoundary`, but for a boundary belonging to a nested `multipart/*`
+    /// payload spawned by [`Field::into_multipart`](crate::Field::into_multipart).
+    ReadingNestedBoundary,
+    ReadingHeaders,
+    ReadingField,
+    CleaningPrevFieldData,
+    /// The nested `Multipart` spawned by [`Field::into_multipart`](crate::Field::into_multipart)
+    /// was dropped before it was drained. Discard the rest of its (still-unparsed) payload,
+    /// up to and including its own terminal boundary line, before resuming the outer stream.
+    CleaningNestedBoundary,
+    Eof,
+}
+
+/// Shared, locked state threaded between a `Multipart` and the `Field`s it yields.
+pub(crate) struct MultipartState {
+    pub(crate) buffer: StreamBuffer,
+    pub(crate) boundary: String,
+    pub(crate) stage: StreamingStage,
+    pub(crate) is_prev_field_consumed: bool,
+    pub(crate) next_field_idx: usize,
+    pub(crate) next_field_waker: Option<Waker>,
+    /// Boundaries of the `Multipart`(s) we descended from via `into_multipart`, innermost
+    /// last; each is popped and restored once the corresponding nested stream is fully
+    /// drained, innermost first.
+    pub(crate) nested_boundaries: Vec<String>,
+    pub(crate) constraints: Option<Constraints>,
+    pub(crate) total_bytes_read: u64,
+}
+
+/// What `StreamBuffer::read_boundary` found at the front of the buffer.
+pub(crate) enum BoundaryLine {
+    /// `--boundary\r\n`: another part follows.
+    Next,
+    /// `--boundary--`: the stream (or nested sub-stream) is exhausted.
+    Eof,
+}
+
+/// Buffers bytes pulled off the underlying stream and slices them into header blocks and
+/// field bodies as the boundary is found.
+pub(crate) struct StreamBuffer {
+    buf: BytesMut,
+    stream: Pin<Box<dyn Stream<Item = crate::Result<Bytes>> + Send + Sync>>,
+    eof: bool,
+}
+
+impl StreamBuffer {
+    pub(crate) fn new<S, O, E>(stream: S) -> Self
+    where
+        S: Stream<Item = std::result::Result<O, E>> + Send + Sync + 'static,
+        O: Into<Bytes> + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>> + 'static,
+    {
+        let stream = stream
+            .map_ok(Into::into)
+            .map_err(|err| crate::Error::StreamReadFailed(err.into()));
+
+        StreamBuffer {
+            buf: BytesMut::new(),
+            stream: Box::pin(stream),
+            eof: false,
+        }
+    }
+
+    /// Pull any data currently available off the underlying stream into the buffer
+    /// without blocking.
+    pub(crate) fn poll_stream(&mut self, cx: &mut Context) -> crate::Result<()> {
+        if self.eof {
+            return Ok(());
+        }
+
+        loop {
+            match self.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(bytes))) => self.buf.extend_from_slice(&bytes),
+                Poll::Ready(Some(Err(err))) => return Err(err),
+                Poll::Ready(None) => {
+                    self.eof = true;
+                    return Ok(());
+                }
+                Poll::Pending => return Ok(()),
+            }
+        }
+    }
+
+    /// Consume the `--boundary\r\n` or terminal `--boundary--` line at the front of the
+    /// buffer. Returns `None` if more data is needed to tell which it is.
+    pub(crate) fn read_boundary(&mut self, boundary: &str) -> crate::Result<Option<BoundaryLine>> {
+        let marker_len = boundary.len() + 2;
+
+        if self.buf.len() < marker_len {
+            return if self.eof {
+                Err(crate::Error::new("Incomplete boundary: the stream ended before a boundary line was found"))
+            } else {
+                Ok(None)
+            };
+        }
+
+        if &self.buf[..2] != b"--" || &self.buf[2..marker_len] != boundary.as_bytes() {
+            return Err(crate::Error::new("Malformed multipart stream: expected a boundary line"));
+        }
+
+        let rest = &self.buf[marker_len..];
+
+        if rest.starts_with(b"--") {
+            let total = marker_len + if rest.len() >= 4 { 4 } else { rest.len() };
+            self.buf.advance(total);
+            return Ok(Some(BoundaryLine::Eof));
+        }
+
+        if rest.len() < 2 {
+            return if self.eof {
+                self.buf.advance(marker_len);
+                Ok(Some(BoundaryLine::Next))
+            } else {
+                Ok(None)
+            };
+        }
+
+        self.buf.advance(marker_len + 2);
+        Ok(Some(BoundaryLine::Next))
+    }
+
+    /// Read the `key: value` header block up to (and including) the blank line that
+    /// terminates it, rejecting it if it grows past `limit` bytes.
+    pub(crate) fn read_headers(&mut self, limit: Option<usize>) -> crate::Result<Option<HeaderMap>> {
+        const HEADERS_END: &[u8] = b"\r\n\r\n";
+
+        let end = find_subsequence(&self.buf, HEADERS_END);
+
+        if let Some(limit) = limit {
+            let scanned = end.map_or(self.buf.len(), |pos| pos + HEADERS_END.len());
+
+            if scanned > limit {
+                return Err(crate::Error::HeadersSizeExceeded { limit });
+            }
+        }
+
+        let end = match end {
+            Some(pos) => pos,
+            None if self.eof => {
+                return Err(crate::Error::new(
+                    "Incomplete headers: the stream ended before the header block was terminated",
+                ))
+            }
+            None => return Ok(None),
+        };
+
+        let header_bytes = self.buf.split_to(end + HEADERS_END.len());
+        let header_str = std::str::from_utf8(&header_bytes[..end])
+            .map_err(|err| crate::Error::new(err.to_string()).context("Couldn't parse part headers as UTF-8"))?;
+
+        let mut headers = HeaderMap::new();
+
+        for line in header_str.split("\r\n") {
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, ':');
+            let name = parts.next().unwrap_or("").trim();
+            let value = parts.next().unwrap_or("").trim();
+
+            if name.is_empty() {
+                continue;
+            }
+
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|err| crate::Error::new(err.to_string()).context("Invalid part header name"))?;
+            let header_value = HeaderValue::from_str(value)
+                .map_err(|err| crate::Error::new(err.to_string()).context("Invalid part header value"))?;
+
+            headers.append(header_name, header_value);
+        }
+
+        Ok(Some(headers))
+    }
+
+    /// Discard bytes up to and including the terminal `--boundary--` line, without
+    /// attempting to parse whatever parts precede it. Used to fast-forward past an
+    /// abandoned nested `multipart/*` payload. Returns `Some(())` once the terminal line
+    /// has been found and consumed, `None` if more data is needed.
+    pub(crate) fn skip_to_boundary_end(&mut self, boundary: &str) -> crate::Result<Option<()>> {
+        let delimiter = format!("--{}--", boundary);
+        let delimiter = delimiter.as_bytes();
+
+        if let Some(pos) = find_subsequence(&self.buf, delimiter) {
+            let mut end = pos + delimiter.len();
+
+            if self.buf[end..].starts_with(b"\r\n") {
+                end += 2;
+            }
+
+            self.buf.advance(end);
+            return Ok(Some(()));
+        }
+
+        if self.eof {
+            return Err(crate::Error::new(
+                "Incomplete nested multipart payload: the stream ended before its terminal boundary was found",
+            ));
+        }
+
+        let safe_len = self.buf.len().saturating_sub(overlap_suffix_len(&self.buf, delimiter));
+        self.buf.advance(safe_len);
+        Ok(None)
+    }
+
+    /// Read up to the next field boundary. The returned `bool` is `true` once the
+    /// boundary (and therefore the end of this field) has been reached.
+    pub(crate) fn read_field_data(&mut self, boundary: &str) -> crate::Result<Option<(bool, Bytes)>> {
+        let delimiter = format!("\r\n--{}", boundary);
+        let delimiter = delimiter.as_bytes();
+
+        if let Some(pos) = find_subsequence(&self.buf, delimiter) {
+            let bytes = self.buf.split_to(pos).freeze();
+            self.buf.advance(2); // drop the delimiter's leading CRLF, which isn't field data
+            return Ok(Some((true, bytes)));
+        }
+
+        if self.eof {
+            return Err(crate::Error::new(
+                "Incomplete field data: the stream ended before a boundary was found",
+            ));
+        }
+
+        let safe_len = self.buf.len().saturating_sub(overlap_suffix_len(&self.buf, delimiter));
+
+        if safe_len == 0 {
+            return Ok(None);
+        }
+
+        let bytes = self.buf.split_to(safe_len).freeze();
+        Ok(Some((false, bytes)))
+    }
+}
+
+fn find_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() || haystack.len() < needle.len() {
+        return None;
+    }
+
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// The length of the longest suffix of `buf` that's also a prefix of `delimiter`, i.e. the
+/// number of trailing bytes that might turn into (the start of) `delimiter` once more data
+/// arrives, and so aren't yet safe to hand out as field data.
+fn overlap_suffix_len(buf: &[u8], delimiter: &[u8]) -> usize {
+    let max = delimiter.len().saturating_sub(1).min(buf.len());
+
+    for len in (1..=max).rev() {
+        if buf[buf.len() - len..] == delimiter[..len] {
+            return len;
+        }
+    }
+
+    0
+}
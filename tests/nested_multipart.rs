@@ -0,0 +1,96 @@
+use bytes::Bytes;
+use futures::stream::once;
+use multer::Multipart;
+use std::convert::Infallible;
+
+fn once_stream(data: &'static str) -> impl futures::stream::Stream<Item = Result<Bytes, Infallible>> {
+    once(async move { Ok(Bytes::from(data)) })
+}
+
+/// A field whose body is itself a `multipart/mixed` payload, descended into via
+/// `into_multipart()`, fully drained, followed by another field on the outer stream.
+/// Regression test: the outer stream used to panic with "expected a boundary line"
+/// because the CRLF the outer framing contributes after the nested terminal boundary
+/// was never consumed before resuming `ReadingBoundary`.
+#[tokio::test]
+async fn resumes_outer_stream_after_draining_nested_multipart() {
+    let data = concat!(
+        "--OUTER\r\n",
+        "Content-Disposition: form-data; name=\"attachment\"\r\n",
+        "Content-Type: multipart/mixed; boundary=INNER\r\n",
+        "\r\n",
+        "--INNER\r\n",
+        "Content-Disposition: form-data; name=\"a.txt\"\r\n",
+        "\r\n",
+        "aaa\r\n",
+        "--INNER\r\n",
+        "Content-Disposition: form-data; name=\"b.txt\"\r\n",
+        "\r\n",
+        "bbb\r\n",
+        "--INNER--\r\n",
+        "\r\n--OUTER\r\n",
+        "Content-Disposition: form-data; name=\"trailer\"\r\n",
+        "\r\n",
+        "ccc\r\n",
+        "--OUTER--\r\n",
+    );
+
+    let mut outer = Multipart::new(once_stream(data), "OUTER");
+
+    let attachment = outer.next_field().await.unwrap().unwrap();
+    let mut inner = attachment.into_multipart().expect("field should be a nested multipart");
+
+    let a = inner.next_field().await.unwrap().unwrap();
+    assert_eq!(a.name(), Some("a.txt"));
+    assert_eq!(a.text().await.unwrap(), "aaa");
+
+    let b = inner.next_field().await.unwrap().unwrap();
+    assert_eq!(b.name(), Some("b.txt"));
+    assert_eq!(b.text().await.unwrap(), "bbb");
+
+    assert!(inner.next_field().await.unwrap().is_none());
+    drop(inner);
+
+    let trailer = outer.next_field().await.unwrap().unwrap();
+    assert_eq!(trailer.name(), Some("trailer"));
+    assert_eq!(trailer.text().await.unwrap(), "ccc");
+
+    assert!(outer.next_field().await.unwrap().is_none());
+}
+
+/// Same outer stream, but the nested `Multipart` is dropped before being drained at all.
+/// Exercises the `CleaningNestedBoundary`/`skip_to_boundary_end` path instead of the
+/// normal `Eof`-reached-through-`next_field` path above.
+#[tokio::test]
+async fn resumes_outer_stream_after_dropping_nested_multipart_early() {
+    let data = concat!(
+        "--OUTER\r\n",
+        "Content-Disposition: form-data; name=\"attachment\"\r\n",
+        "Content-Type: multipart/mixed; boundary=INNER\r\n",
+        "\r\n",
+        "--INNER\r\n",
+        "Content-Disposition: form-data; name=\"a.txt\"\r\n",
+        "\r\n",
+        "aaa\r\n",
+        "--INNER--\r\n",
+        "\r\n--OUTER\r\n",
+        "Content-Disposition: form-data; name=\"trailer\"\r\n",
+        "\r\n",
+        "ccc\r\n",
+        "--OUTER--\r\n",
+    );
+
+    let mut outer = Multipart::new(once_stream(data), "OUTER");
+
+    let attachment = outer.next_field().await.unwrap().unwrap();
+    let inner = attachment.into_multipart().expect("field should be a nested multipart");
+
+    // Drop without ever calling `next_field()` on it.
+    drop(inner);
+
+    let trailer = outer.next_field().await.unwrap().unwrap();
+    assert_eq!(trailer.name(), Some("trailer"));
+    assert_eq!(trailer.text().await.unwrap(), "ccc");
+
+    assert!(outer.next_field().await.unwrap().is_none());
+}